@@ -67,6 +67,59 @@ impl Vector {
         }
         min_index
     }
+
+    /// get index with maximal value
+    pub fn argmax(&self) -> usize {
+        let mut max_index = 0;
+        let mut max_value = self.data[0];
+
+        for (i,v) in self.data.iter().enumerate() {
+            if v > &max_value {
+                max_value = v.clone();
+                max_index = i.clone();
+            }
+        }
+        max_index
+    }
+
+    /// Raw values as a plain Vec
+    pub fn to_vec(&self) -> Vec<f64> { self.data.clone() }
+
+    /// Add a constant to every element of the vector
+    pub fn add_constant(&self, c: f64) -> Vector {
+        Vector::new(self.data.iter().map(|x| x + c).collect())
+    }
+
+    /// Convert -log2(p) values back to linear probabilities
+    pub fn exp2(&self) -> Vector {
+        Vector::new(self.data.iter().map(|x| (-x).exp2()).collect())
+    }
+
+    /// Draw a categorical index from this vector, treated as (possibly unnormalized)
+    /// linear probabilities, by walking its cumulative sum until it passes `u * total`.
+    /// `u` should be uniform in [0, 1). Clamps to the last index so floating point
+    /// drift in the cumulative sum can never walk off the end.
+    pub fn sample(&self, u: f64) -> usize {
+        let total: f64 = self.data.iter().sum();
+        let target = u * total;
+        let mut acc = 0.;
+        for (i, &p) in self.data.iter().enumerate() {
+            acc += p;
+            if acc >= target { return i }
+        }
+        self.data.len() - 1
+    }
+
+    /// Combine the elements of this vector, which are expected to hold -log2(p) values,
+    /// into -log2(sum of the p's) without converting back to linear probabilities.
+    /// Uses the identity -log2(Σp) = min(x) - log2(Σ 2^-(x - min(x))), so it stays
+    /// numerically stable even when the individual probabilities underflow.
+    pub fn log_sum_exp(&self) -> f64 {
+        let min_value = self.data.iter().cloned().fold(f64::MAX, f64::min);
+        if min_value == f64::MAX { return f64::MAX }
+        let sum: f64 = self.data.iter().map(|&x| (min_value - x).exp2()).sum();
+        min_value - sum.log2()
+    }
 }
 
 
@@ -81,7 +134,13 @@ impl Matrix {
         Some(Matrix { rows: data.len(), cols: cols, data: data })
     }
 
-//    pub fn rows(&self) -> usize { self.rows }
+    /// A matrix with no rows, for callers that need to return "no result" without
+    /// the `Option`/`Vec` that `new` otherwise requires (it rejects empty input).
+    pub fn empty() -> Matrix {
+        Matrix { rows: 0, cols: 0, data: vec![] }
+    }
+
+    pub fn rows(&self) -> usize { self.rows }
     pub fn cols(&self) -> usize { self.cols }
 
     /// Positive matrix has all its entries >= 0
@@ -98,6 +157,16 @@ impl Matrix {
                  data: self.data.iter().map(|r| r.iter().map(|i| -i.log2()).collect()).collect()}
     }
 
+    /// Convert -log2(p) values back to linear probabilities
+    pub fn exp2(&self) -> Matrix {
+        Matrix { rows: self.rows,
+                 cols: self.cols,
+                 data: self.data.iter().map(|r| r.iter().map(|i| (-i).exp2()).collect()).collect()}
+    }
+
+    /// Value at a given row and column
+    pub fn get(&self, row: usize, col: usize) -> f64 { self.data[row][col] }
+
     /// Get copy of a given column
     pub fn column(&self, index: usize) -> Option<Vector> {
         // Validate input
@@ -106,6 +175,12 @@ impl Matrix {
         Some(Vector::new(data))
     }
 
+    /// Get copy of a given row
+    pub fn row(&self, index: usize) -> Option<Vector> {
+        if index >= self.rows { return None }
+        Some(Vector::new(self.data[index].clone()))
+    }
+
     /// Add vector to each column
     pub fn add_to_columns(&self, v: &Vector) -> Matrix {
         let n = cmp::min(self.rows, v.len());
@@ -121,6 +196,21 @@ impl Matrix {
                  data: data}
     }
 
+    /// Add a vector to each row: data[i][j] += v[j]
+    pub fn add_to_rows(&self, v: &Vector) -> Matrix {
+        let n = cmp::min(self.cols, v.len());
+        let mut data = self.data.clone();
+
+        for row in data.iter_mut() {
+            for j in 0..n {
+                row[j] += v.get(j)
+            }
+        }
+        Matrix { rows: self.rows,
+                 cols: self.cols,
+                 data: data}
+    }
+
     /// Maximum by column
     pub fn min_by_column(&self) -> Vector {
         let mut v = vec![f64::MAX; self.cols];
@@ -147,6 +237,117 @@ impl Matrix {
         }
         args
     }
+
+    /// For each column j, combine (row_i[j] + v[i]) across rows i using log-sum-exp.
+    /// This is the log-domain equivalent of `transitions^T · v`, used by the forward pass.
+    pub fn log_sum_exp_by_column(&self, v: &Vector) -> Vector {
+        let mat = self.add_to_columns(v);
+        let data = (0..mat.cols).map(|j| mat.column(j).unwrap().log_sum_exp()).collect();
+        Vector::new(data)
+    }
+
+    /// For each row i, combine (row_i[j] + v[j]) across columns j using log-sum-exp.
+    /// This is the log-domain equivalent of `transitions · v`, used by the backward pass.
+    pub fn log_sum_exp_by_row(&self, v: &Vector) -> Vector {
+        let mat = self.add_to_rows(v);
+        let data = (0..mat.rows).map(|i| mat.row(i).unwrap().log_sum_exp()).collect();
+        Vector::new(data)
+    }
+
+    /// n x n identity matrix
+    pub fn identity(n: usize) -> Matrix {
+        let data = (0..n).map(|i| (0..n).map(|j| if i == j {1.} else {0.}).collect()).collect();
+        Matrix { rows: n, cols: n, data: data }
+    }
+
+    /// Elementwise subtraction
+    pub fn subtract(&self, other: &Matrix) -> Matrix {
+        let data = self.data.iter().zip(&other.data)
+            .map(|(r1, r2)| r1.iter().zip(r2).map(|(a, b)| a - b).collect())
+            .collect();
+        Matrix { rows: self.rows, cols: self.cols, data: data }
+    }
+
+    /// Extract the square submatrix restricted to the given row/column indices
+    pub fn submatrix(&self, indices: &[usize]) -> Matrix {
+        let data = indices.iter()
+            .map(|&i| indices.iter().map(|&j| self.data[i][j]).collect())
+            .collect();
+        Matrix { rows: indices.len(), cols: indices.len(), data: data }
+    }
+
+    /// Multiply a row vector by this matrix: result[j] = Σ_i v[i]·data[i][j]
+    pub fn mul_vector(&self, v: &Vector) -> Vector {
+        let mut result = vec![0.; self.cols];
+        for i in 0..self.rows {
+            for j in 0..self.cols {
+                result[j] += v.get(i) * self.data[i][j];
+            }
+        }
+        Vector::new(result)
+    }
+
+    /// Invert a square matrix via Gauss-Jordan elimination with partial pivoting.
+    /// Returns None if the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix> {
+        if self.rows != self.cols { return None }
+        let n = self.rows;
+        let mut left = self.data.clone();
+        let mut right: Vec<Vec<f64>> = (0..n)
+            .map(|i| (0..n).map(|j| if i == j {1.} else {0.}).collect())
+            .collect();
+
+        for col in 0..n {
+            let mut pivot = col;
+            for row in (col + 1)..n {
+                if left[row][col].abs() > left[pivot][col].abs() { pivot = row }
+            }
+            if left[pivot][col].abs() < 1e-12 { return None }
+            left.swap(col, pivot);
+            right.swap(col, pivot);
+
+            let scale = left[col][col];
+            for j in 0..n { left[col][j] /= scale; right[col][j] /= scale }
+
+            for row in 0..n {
+                if row == col { continue }
+                let factor = left[row][col];
+                if factor == 0. { continue }
+                for j in 0..n {
+                    left[row][j] -= factor * left[col][j];
+                    right[row][j] -= factor * right[col][j];
+                }
+            }
+        }
+
+        Matrix::new(right)
+    }
+
+    /// Fundamental matrix N = (I - Q)⁻¹ of an absorbing Markov chain, where Q is this
+    /// matrix restricted to `transient` rows/columns. `N[i][j]` is the expected number
+    /// of visits to transient state `transient[j]` before absorption, starting from
+    /// `transient[i]`. Returns None if `I - Q` is singular.
+    pub fn fundamental_matrix(&self, transient: &[usize]) -> Option<Matrix> {
+        let q = self.submatrix(transient);
+        Matrix::identity(transient.len()).subtract(&q).inverse()
+    }
+
+    /// Dominant left eigenvector of this row-stochastic matrix (its stationary
+    /// distribution), found by power iteration starting from the uniform distribution.
+    pub fn stationary_distribution(&self) -> Vector {
+        let n = self.rows;
+        let mut v = Vector::new(vec![1. / n as f64; n]);
+
+        for _ in 0..1000 {
+            let next = self.mul_vector(&v);
+            let total: f64 = (0..n).map(|i| next.get(i)).sum();
+            let normalized = Vector::new((0..n).map(|i| next.get(i) / total).collect());
+            let delta: f64 = (0..n).map(|i| (normalized.get(i) - v.get(i)).abs()).sum();
+            v = normalized;
+            if delta < 1e-12 { break }
+        }
+        v
+    }
 }
 
 
@@ -240,4 +441,81 @@ mod tests {
         assert!(v.argmin() == 2);
     }
 
+    #[test]
+    fn test_argmax() {
+        let v = Vector::new(vec![1., 4., 0.34, 12.]);
+        assert!(v.argmax() == 3);
+    }
+
+    #[test]
+    fn test_log_sum_exp() {
+        // -log2(0.5) and -log2(0.25) should combine into -log2(0.75)
+        let v = Vector::new(vec![0.5f64.log2() * -1., 0.25f64.log2() * -1.]);
+        let expected = -0.75f64.log2();
+        assert!((v.log_sum_exp() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_exp2_round_trip() {
+        let v = Vector::new(vec![0.5, 0.25]);
+        let back = v.minus_log().exp2();
+        assert!((back.get(0) - 0.5).abs() < 1e-9);
+        assert!((back.get(1) - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sample() {
+        let v = Vector::new(vec![0.25, 0.75]);
+        assert!(v.sample(0.0) == 0);
+        assert!(v.sample(0.999) == 1);
+    }
+
+    #[test]
+    fn test_inverse() {
+        let mat = Matrix::new(vec![vec![2., 0.],
+                                   vec![0., 2.]]).unwrap();
+        let expected = Matrix::new(vec![vec![0.5, 0.],
+                                        vec![0., 0.5]]).unwrap();
+        assert!(mat.inverse() == Some(expected));
+    }
+
+    #[test]
+    fn test_inverse_singular() {
+        let mat = Matrix::new(vec![vec![1., 1.],
+                                   vec![1., 1.]]).unwrap();
+        assert!(mat.inverse().is_none());
+    }
+
+    #[test]
+    fn test_fundamental_matrix() {
+        // State 0 has a 0.5 chance of self-looping and a 0.5 chance of leaving to an
+        // absorbing state outside the transient set, so it takes 2 steps on average
+        // before leaving.
+        let mat = Matrix::new(vec![vec![0.5, 0.5],
+                                   vec![0., 1.]]).unwrap();
+        let n = mat.fundamental_matrix(&[0]).unwrap();
+        assert!((n.get(0, 0) - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stationary_distribution() {
+        let mat = Matrix::new(vec![vec![0.75, 0.25],
+                                   vec![0.25, 0.75]]).unwrap();
+        let pi = mat.stationary_distribution();
+        assert!((pi.get(0) - 0.5).abs() < 1e-6);
+        assert!((pi.get(1) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_log_sum_exp_by_column() {
+        let mat = Matrix::new(vec![vec![1., 2.],
+                                   vec![3., 4.]]).unwrap();
+        let v = Vector::new(vec![0., 0.]);
+        let result = mat.log_sum_exp_by_column(&v);
+        let expected = Vector::new(vec![-((-1f64).exp2() + (-3f64).exp2()).log2(),
+                                        -((-2f64).exp2() + (-4f64).exp2()).log2()]);
+        assert!((result.get(0) - expected.get(0)).abs() < 1e-9);
+        assert!((result.get(1) - expected.get(1)).abs() < 1e-9);
+    }
+
 }
\ No newline at end of file
@@ -30,6 +30,11 @@
 // instead of multiplication.  And since we are interested in probabilities in range [0, 1]
 // We will operate on -log. So instead of max probability we will minimize log probabilities.
 
+extern crate rand;
+
+use std::f64::consts::PI;
+
+use self::rand::Rng;
 use matrices::{Vector, Matrix};
 
 
@@ -50,6 +55,9 @@ pub struct HiddenMarkov {
     // Observation model. This matrix contains states as a rows and possible outcomes as columns
     // So the size of this matrix is: #states x #outcomes
     observation_model: Matrix,
+    // Probability of the sequence ending at each state. None means every state is an
+    // equally plausible stopping point.
+    end_states: Option<Vector>,
 }
 
 impl HiddenMarkov {
@@ -97,12 +105,32 @@ impl HiddenMarkov {
                 labels_count: num_outcomes,
                 init_states: is_log,
                 state_transitions: trans_log,
-                observation_model: obs_log
+                observation_model: obs_log,
+                end_states: None
             }
         )
 
     }
 
+    /// Like `new`, but also specifies the probability of the sequence ending at each
+    /// state. `end_probs` must have one positive entry per state. The final step of
+    /// `map_estimate` and `log_likelihood` then weighs each end state by it, instead of
+    /// treating all states as equally plausible stopping points.
+    pub fn with_end_states(initials: Vec<f64>, transitions: Vec<Vec<f64>>,
+                            observation_model: Vec<Vec<f64>>, end_probs: Vec<f64>) -> Option<HiddenMarkov>
+    {
+        let hmm = HiddenMarkov::new(initials, transitions, observation_model);
+        if hmm.is_none() { return None }
+        let mut hmm = hmm.unwrap();
+
+        let ends = Vector::new(end_probs);
+        if ends.len() != hmm.init_states.len() { return None }
+        if !ends.is_positive() { return None }
+
+        hmm.end_states = Some(ends.minus_log());
+        Some(hmm)
+    }
+
     /// Calculate MAP (Maximum a posteriori) using Viterbi algorithm
     /// As a input provide list of observations and as a output this function will provide
     /// The most probable sequence of states which generates such observations
@@ -124,9 +152,13 @@ impl HiddenMarkov {
             tracebacks.push(t);
         }
 
-        // Based on the last message select most probable end state
+        // Based on the last message select most probable end state. If some states are
+        // implausible stopping points, weigh them by their end probability first.
         let mut states: Vec<StateId> = vec![0; obs_len];
-        let mut last_state = last_msg.argmin();
+        let mut last_state = match &self.end_states {
+            Some(end_log) => last_msg.add_vector(end_log).argmin(),
+            None => last_msg.argmin(),
+        };
         for i in (0..obs_len).rev() {
             let state: StateId = tracebacks[i][last_state];
             last_state = state;
@@ -137,12 +169,228 @@ impl HiddenMarkov {
         states
     }
 
+    /// Sample a synthetic sequence of length `n` from this model: draw the first state
+    /// from `init_states`, then at each step draw the emitted label from that state's
+    /// row of the observation model and the next state from its row of the transition
+    /// matrix. Returns the states and the labels they generated.
+    pub fn generate<R: Rng>(&self, n: usize, rng: &mut R) -> (Vec<StateId>, Vec<LabelId>) {
+        let mut states: Vec<StateId> = Vec::with_capacity(n);
+        let mut labels: Vec<LabelId> = Vec::with_capacity(n);
+        if n == 0 { return (states, labels) }
+
+        let mut state = self.init_states.exp2().sample(rng.gen());
+        for _ in 0..n {
+            states.push(state);
+            labels.push(self.observation_model.row(state).unwrap().exp2().sample(rng.gen()));
+            state = self.state_transitions.row(state).unwrap().exp2().sample(rng.gen());
+        }
+        (states, labels)
+    }
+
+    /// Per-position posterior decoding: run the forward and backward passes and return
+    /// the γ matrix (positions × states), where `γ[t][i]` is P(state_t = i | O),
+    /// computed as γ_t(i) ∝ α_t(i)·β_t(i) and normalized per position. Complements
+    /// `map_estimate`'s single best joint path with calibrated per-position confidences.
+    pub fn posterior(&self, observations: &[LabelId]) -> Matrix {
+        // Validate input, same as map_estimate
+        if observations.is_empty() { return Matrix::empty() }
+        if observations.iter().any(|&x| x >= self.labels_count) { return Matrix::empty() }
+
+        let alpha = self.forward(observations);
+        let beta = self.backward(observations);
+
+        let rows: Vec<Vec<f64>> = alpha.iter().zip(beta.iter()).map(|(a, b)| {
+            let combined = a.add_vector(b);
+            let normalizer = combined.log_sum_exp();
+            combined.add_constant(-normalizer).exp2().to_vec()
+        }).collect();
+
+        Matrix::new(rows).unwrap()
+    }
+
+    /// Most likely state at each position, i.e. `argmax_i γ_t(i)` for every t.
+    pub fn posterior_decode(&self, observations: &[LabelId]) -> Vec<StateId> {
+        let gamma = self.posterior(observations);
+        (0..gamma.rows()).map(|t| gamma.row(t).unwrap().argmax()).collect()
+    }
+
+    /// Expected number of visits to each of the `transient` states before absorption,
+    /// starting from `start`, treating every state outside `transient` as absorbing.
+    /// Computed from the fundamental matrix N = (I - Q)⁻¹, where Q is the transition
+    /// matrix restricted to `transient`. Returns None if `start` is not transient, or
+    /// the chain can loop inside `transient` forever (I - Q singular).
+    pub fn node_visits(&self, transient: &[StateId], start: StateId) -> Option<Vector> {
+        let local_start = match transient.iter().position(|&s| s == start) {
+            Some(i) => i,
+            None => return None,
+        };
+        let linear = self.state_transitions.exp2();
+        let n = linear.fundamental_matrix(transient);
+        if n.is_none() { return None }
+        n.unwrap().row(local_start)
+    }
+
+    /// Expected number of steps to reach an absorbing state, starting from `start`.
+    /// See `node_visits` for the semantics of `transient`.
+    pub fn expected_length(&self, transient: &[StateId], start: StateId) -> Option<f64> {
+        self.node_visits(transient, start).map(|visits| visits.to_vec().iter().sum())
+    }
+
+    /// Relative entropy rate between this model's transition structure and `other`'s:
+    /// Σ_i π_i Σ_j a_ij·log(a_ij / a'_ij), where π is this model's stationary
+    /// distribution (the Perron eigenvector of its transition matrix, found by power
+    /// iteration). Returns None if the two models don't have the same number of states.
+    pub fn kullback_leibler(&self, other: &HiddenMarkov) -> Option<f64> {
+        if self.init_states.len() != other.init_states.len() { return None }
+
+        let a = self.state_transitions.exp2();
+        let b = other.state_transitions.exp2();
+        let pi = a.stationary_distribution();
+
+        let mut divergence = 0.;
+        for i in 0..a.rows() {
+            let row_a = a.row(i).unwrap();
+            let row_b = b.row(i).unwrap();
+            let mut rate = 0.;
+            for j in 0..row_a.len() {
+                let p = row_a.get(j);
+                let q = row_b.get(j);
+                if p > 0. { rate += p * (p / q).ln() }
+            }
+            divergence += pi.get(i) * rate;
+        }
+        Some(divergence)
+    }
+
+    /// Calculate how probable the given observation sequence is under this model:
+    /// log P(O|λ) = log Σ_i α_T(i)·end_i, computed with the forward algorithm (end_i is
+    /// 1 for every state unless `with_end_states` was used to weigh end states).
+    /// Unlike `map_estimate`, which only returns the single best state path, this lets
+    /// you compare models or score a sequence without decoding it.
+    pub fn log_likelihood(&self, observations: &[LabelId]) -> f64 {
+        // Validate input, same as map_estimate: the empty sequence has probability 1
+        // (log-likelihood 0), and a label the model can't emit has probability 0.
+        if observations.is_empty() { return 0.0 }
+        if observations.iter().any(|&x| x >= self.labels_count) { return f64::NEG_INFINITY }
+
+        let alpha = self.forward(observations);
+        let last = &alpha[observations.len() - 1];
+        let weighted = match &self.end_states {
+            Some(end_log) => last.add_vector(end_log),
+            None => last.clone(),
+        };
+        // forward() keeps everything as -log2, so negate log_sum_exp() to get back
+        // the usual (natural-log-free) log-likelihood.
+        -weighted.log_sum_exp()
+    }
+
+    /// Learn `init_states`, `state_transitions` and `observation_model` from a batch of
+    /// observation sequences using the Baum-Welch (forward-backward) algorithm.
+    /// Re-estimates the parameters once per iteration and stops once the total
+    /// log-likelihood of `sequences` improves by less than `tol`, or after `max_iter`
+    /// iterations, whichever comes first.
+    pub fn train(&mut self, sequences: &[Vec<LabelId>], max_iter: usize, tol: f64) {
+        let num_states = self.init_states.len();
+        let mut prev_log_likelihood = f64::MAX;
+
+        for _ in 0..max_iter {
+            let mut pi_num = vec![0.; num_states];
+            let mut trans_num = vec![vec![0.; num_states]; num_states];
+            let mut trans_denom = vec![0.; num_states];
+            let mut obs_num = vec![vec![0.; self.labels_count]; num_states];
+            let mut obs_denom = vec![0.; num_states];
+            let mut total_log_likelihood = 0.;
+            let mut num_sequences = 0.;
+
+            for sequence in sequences {
+                let seq_len = sequence.len();
+                if seq_len == 0 { continue }
+                num_sequences += 1.;
+
+                let alpha = self.forward(sequence);
+                let beta = self.backward(sequence);
+                total_log_likelihood += alpha[seq_len - 1].log_sum_exp();
+
+                for t in 0..seq_len {
+                    // γ_t(i) ∝ α_t(i)·β_t(i), normalized per time step
+                    let combined = alpha[t].add_vector(&beta[t]);
+                    let normalizer = combined.log_sum_exp();
+                    let gamma = combined.add_constant(-normalizer);
+
+                    for i in 0..num_states {
+                        let g = (-gamma.get(i)).exp2();
+                        if t == 0 { pi_num[i] += g }
+                        obs_num[i][sequence[t]] += g;
+                        obs_denom[i] += g;
+                        if t < seq_len - 1 { trans_denom[i] += g }
+                    }
+
+                    // ξ_t(i,j) ∝ α_t(i)·a_ij·b_j(o_{t+1})·β_{t+1}(j), same normalizer as γ_t
+                    if t < seq_len - 1 {
+                        let next = self.state_from_observation(sequence[t + 1]).add_vector(&beta[t + 1]);
+                        for i in 0..num_states {
+                            for j in 0..num_states {
+                                let xi_log = alpha[t].get(i) + self.state_transitions.get(i, j)
+                                    + next.get(j) - normalizer;
+                                trans_num[i][j] += (-xi_log).exp2();
+                            }
+                        }
+                    }
+                }
+            }
+
+            if num_sequences == 0. { return }
+
+            let new_pi: Vec<f64> = pi_num.iter().map(|&x| x / num_sequences).collect();
+            let new_trans: Vec<Vec<f64>> = (0..num_states)
+                .map(|i| (0..num_states).map(|j| trans_num[i][j] / trans_denom[i]).collect())
+                .collect();
+            let new_obs: Vec<Vec<f64>> = (0..num_states)
+                .map(|i| (0..self.labels_count).map(|k| obs_num[i][k] / obs_denom[i]).collect())
+                .collect();
+
+            self.init_states = Vector::new(new_pi).minus_log();
+            self.state_transitions = Matrix::new(new_trans).unwrap().minus_log();
+            self.observation_model = Matrix::new(new_obs).unwrap().minus_log();
+
+            if (prev_log_likelihood - total_log_likelihood).abs() < tol { break }
+            prev_log_likelihood = total_log_likelihood;
+        }
+    }
+
     // The probability of being in given state based on the observation.
     // This probability is column in observation_model
     fn state_from_observation(&self, obs: LabelId) -> Vector {
         self.observation_model.column(obs).unwrap()
     }
 
+    /// Forward pass: α_1(i) = π_i·b_i(o_1), α_{t+1}(j) = (Σ_i α_t(i)·a_ij)·b_j(o_{t+1}).
+    /// Returned as -log2 values, one Vector per time step.
+    fn forward(&self, observations: &[LabelId]) -> Vec<Vector> {
+        let mut alpha: Vec<Vector> = Vec::with_capacity(observations.len());
+        alpha.push(self.init_states.add_vector(&self.state_from_observation(observations[0])));
+
+        for t in 1..observations.len() {
+            let msg = self.state_transitions.log_sum_exp_by_column(&alpha[t - 1]);
+            alpha.push(msg.add_vector(&self.state_from_observation(observations[t])));
+        }
+        alpha
+    }
+
+    /// Backward pass: β_T(i) = 1, β_t(i) = Σ_j a_ij·b_j(o_{t+1})·β_{t+1}(j).
+    /// Returned as -log2 values, one Vector per time step.
+    fn backward(&self, observations: &[LabelId]) -> Vec<Vector> {
+        let num_states = self.init_states.len();
+        let obs_len = observations.len();
+        let mut beta: Vec<Vector> = vec![Vector::new(vec![0.; num_states]); obs_len];
+
+        for t in (0..obs_len - 1).rev() {
+            let next = self.state_from_observation(observations[t + 1]).add_vector(&beta[t + 1]);
+            beta[t] = self.state_transitions.log_sum_exp_by_row(&next);
+        }
+        beta
+    }
+
     /// Calculate message and traceback
     /// Message is minimal value across columns, trace back is argmax from columns
     fn next_msg_and_traceback(&self, phi: &Vector) -> (Vector, Vec<StateId>) {
@@ -152,6 +400,206 @@ impl HiddenMarkov {
 }
 
 
+/// Specialized structure for a Hidden Markov Model of order 1 with univariate Gaussian
+/// emissions, for real-valued observation sequences instead of discrete labels.
+/// Reuses the same log-domain Viterbi/Baum-Welch machinery as `HiddenMarkov`, swapping
+/// the discrete observation lookup for a Gaussian log-density evaluation.
+// The values in this structure are converted to the log, same as HiddenMarkov.
+pub struct GaussianHmm {
+    // Probability of starting states. Row Id == state id
+    init_states: Vector,
+    // Probability table of switching states
+    state_transitions: Matrix,
+    // Mean of each state's emission distribution
+    means: Vector,
+    // Variance of each state's emission distribution
+    variances: Vector,
+}
+
+impl GaussianHmm {
+    /// Create a new Gaussian Hidden Markov Model
+    /// Please note that:
+    ///   * initials should have more then 1 state. Its values should be positive
+    ///   * transitions should have exactly #state x #state elements. Values positive
+    ///   * means and variances should have one entry per state. Variances must be positive
+    pub fn new(initials: Vec<f64>, transitions: Vec<Vec<f64>>,
+               means: Vec<f64>, variances: Vec<f64>) -> Option<GaussianHmm>
+    {
+        let num_states = initials.len();
+        let is = Vector::new(initials);
+        let ts = Matrix::new(transitions);
+
+        // Validate parameters
+        if num_states < 2 { return None }
+        if !is.is_positive() { return None }
+        if ts.is_none() { return None }
+        let trans = ts.unwrap();
+        if !trans.is_positive() { return None }
+        if means.len() != num_states || variances.len() != num_states { return None }
+        if variances.iter().any(|&v| v <= 0.) { return None }
+
+        Some(
+            GaussianHmm {
+                init_states: is.minus_log(),
+                state_transitions: trans.minus_log(),
+                means: Vector::new(means),
+                variances: Vector::new(variances),
+            }
+        )
+    }
+
+    /// Calculate MAP (Maximum a posteriori) using Viterbi algorithm.
+    /// Mirrors `HiddenMarkov::map_estimate`, but with Gaussian emissions instead of a
+    /// discrete observation lookup table.
+    pub fn map_estimate(&self, observations: Vec<f64>) -> Vec<StateId> {
+        let obs_len = observations.len();
+        if obs_len == 0 { return vec![] }
+
+        let mut last_msg: Vector = self.init_states.clone();
+        let mut tracebacks: Vec<Vec<StateId>> = Vec::with_capacity(obs_len);
+
+        for i in 0..obs_len {
+            let phi = last_msg.add_vector(&self.state_from_observation(observations[i]));
+            let mat = self.state_transitions.add_to_columns(&phi);
+            last_msg = mat.min_by_column();
+            tracebacks.push(mat.argmin_by_column());
+        }
+
+        let mut states: Vec<StateId> = vec![0; obs_len];
+        let mut last_state = last_msg.argmin();
+        for i in (0..obs_len).rev() {
+            let state: StateId = tracebacks[i][last_state];
+            last_state = state;
+            states[i] = state;
+        }
+
+        states
+    }
+
+    /// Learn `init_states`, `state_transitions` and each state's Gaussian emission
+    /// parameters (`means`/`variances`) from a batch of real-valued observation
+    /// sequences via Baum-Welch. Mirrors `HiddenMarkov::train`, except the emission
+    /// re-estimation step computes γ-weighted sample means/variances instead of
+    /// discrete label counts.
+    pub fn train(&mut self, sequences: &[Vec<f64>], max_iter: usize, tol: f64) {
+        let num_states = self.init_states.len();
+        let mut prev_log_likelihood = f64::MAX;
+
+        for _ in 0..max_iter {
+            let mut pi_num = vec![0.; num_states];
+            let mut trans_num = vec![vec![0.; num_states]; num_states];
+            let mut trans_denom = vec![0.; num_states];
+            let mut mean_num = vec![0.; num_states];
+            let mut obs_denom = vec![0.; num_states];
+            let mut weighted_obs: Vec<(StateId, f64, f64)> = Vec::new();
+            let mut total_log_likelihood = 0.;
+            let mut num_sequences = 0.;
+
+            for sequence in sequences {
+                let seq_len = sequence.len();
+                if seq_len == 0 { continue }
+                num_sequences += 1.;
+
+                let alpha = self.forward(sequence);
+                let beta = self.backward(sequence);
+                total_log_likelihood += alpha[seq_len - 1].log_sum_exp();
+
+                for t in 0..seq_len {
+                    // γ_t(i) ∝ α_t(i)·β_t(i), normalized per time step
+                    let combined = alpha[t].add_vector(&beta[t]);
+                    let normalizer = combined.log_sum_exp();
+                    let gamma = combined.add_constant(-normalizer);
+
+                    for i in 0..num_states {
+                        let g = (-gamma.get(i)).exp2();
+                        if t == 0 { pi_num[i] += g }
+                        mean_num[i] += g * sequence[t];
+                        obs_denom[i] += g;
+                        weighted_obs.push((i, g, sequence[t]));
+                        if t < seq_len - 1 { trans_denom[i] += g }
+                    }
+
+                    // ξ_t(i,j) ∝ α_t(i)·a_ij·b_j(o_{t+1})·β_{t+1}(j), same normalizer as γ_t
+                    if t < seq_len - 1 {
+                        let next = self.state_from_observation(sequence[t + 1]).add_vector(&beta[t + 1]);
+                        for i in 0..num_states {
+                            for j in 0..num_states {
+                                let xi_log = alpha[t].get(i) + self.state_transitions.get(i, j)
+                                    + next.get(j) - normalizer;
+                                trans_num[i][j] += (-xi_log).exp2();
+                            }
+                        }
+                    }
+                }
+            }
+
+            if num_sequences == 0. { return }
+
+            let new_pi: Vec<f64> = pi_num.iter().map(|&x| x / num_sequences).collect();
+            let new_trans: Vec<Vec<f64>> = (0..num_states)
+                .map(|i| (0..num_states).map(|j| trans_num[i][j] / trans_denom[i]).collect())
+                .collect();
+            let new_means: Vec<f64> = (0..num_states).map(|i| mean_num[i] / obs_denom[i]).collect();
+
+            let mut var_num = vec![0.; num_states];
+            for &(i, g, o) in &weighted_obs {
+                var_num[i] += g * (o - new_means[i]).powi(2);
+            }
+            let new_vars: Vec<f64> = (0..num_states).map(|i| var_num[i] / obs_denom[i]).collect();
+
+            self.init_states = Vector::new(new_pi).minus_log();
+            self.state_transitions = Matrix::new(new_trans).unwrap().minus_log();
+            self.means = Vector::new(new_means);
+            self.variances = Vector::new(new_vars);
+
+            if (prev_log_likelihood - total_log_likelihood).abs() < tol { break }
+            prev_log_likelihood = total_log_likelihood;
+        }
+    }
+
+    // -log2 density of observing `obs` under each state's Gaussian emission model.
+    // The usual Gaussian -ln density is divided by ln(2) to keep it on the same -log2
+    // scale as `init_states`/`state_transitions`, so they can be added directly.
+    fn state_from_observation(&self, obs: f64) -> Vector {
+        let ln2 = 2f64.ln();
+        let data = (0..self.means.len()).map(|i| {
+            let mu = self.means.get(i);
+            let var = self.variances.get(i);
+            let neg_ln_density = 0.5 * (2. * PI * var).ln() + (obs - mu).powi(2) / (2. * var);
+            neg_ln_density / ln2
+        }).collect();
+        Vector::new(data)
+    }
+
+    /// Forward pass, see `HiddenMarkov::forward`. Returned as -log2 values, one
+    /// Vector per time step.
+    fn forward(&self, observations: &[f64]) -> Vec<Vector> {
+        let mut alpha: Vec<Vector> = Vec::with_capacity(observations.len());
+        alpha.push(self.init_states.add_vector(&self.state_from_observation(observations[0])));
+
+        for t in 1..observations.len() {
+            let msg = self.state_transitions.log_sum_exp_by_column(&alpha[t - 1]);
+            alpha.push(msg.add_vector(&self.state_from_observation(observations[t])));
+        }
+        alpha
+    }
+
+    /// Backward pass, see `HiddenMarkov::backward`. Returned as -log2 values, one
+    /// Vector per time step.
+    fn backward(&self, observations: &[f64]) -> Vec<Vector> {
+        let num_states = self.init_states.len();
+        let obs_len = observations.len();
+        let mut beta: Vec<Vector> = vec![Vector::new(vec![0.; num_states]); obs_len];
+
+        for t in (0..obs_len - 1).rev() {
+            let next = self.state_from_observation(observations[t + 1]).add_vector(&beta[t + 1]);
+            beta[t] = self.state_transitions.log_sum_exp_by_row(&next);
+        }
+        beta
+    }
+}
+
+
 /// ------------------------------------------------------------------------------------------------
 /// Module unit tests
 /// ------------------------------------------------------------------------------------------------
@@ -193,4 +641,217 @@ mod tests {
         assert!(estimate == vec![0, 0, 1, 1, 1])
     }
 
+    #[test]
+    fn test_posterior() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+        let gamma = hmm.posterior(&vec![0, 0, 1, 1, 1]);
+
+        // Each position's posterior is a probability distribution over states
+        for t in 0..gamma.rows() {
+            let row = gamma.row(t).unwrap();
+            let total: f64 = (0..row.len()).map(|i| row.get(i)).sum();
+            assert!((total - 1.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_posterior_guards_empty_and_invalid_input() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+
+        assert_eq!(hmm.posterior(&vec![]).rows(), 0);
+        assert_eq!(hmm.posterior(&vec![2]).rows(), 0);
+        assert_eq!(hmm.posterior_decode(&vec![]), vec![]);
+        assert_eq!(hmm.posterior_decode(&vec![2]), vec![]);
+    }
+
+    #[test]
+    fn test_posterior_decode_matches_map_estimate() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+        let observations = vec![0, 0, 1, 1, 1];
+
+        assert!(hmm.posterior_decode(&observations) == hmm.map_estimate(observations));
+    }
+
+    #[test]
+    fn test_generate() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+        let mut rng = rand::weak_rng();
+
+        let (states, labels) = hmm.generate(10, &mut rng);
+        assert!(states.len() == 10);
+        assert!(labels.len() == 10);
+        assert!(states.iter().all(|&s| s < 2));
+        assert!(labels.iter().all(|&l| l < 2));
+    }
+
+    #[test]
+    fn test_log_likelihood() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+
+        // State 1's emissions strongly favor label 1 (0.75) while state 0 is neutral
+        // between the two labels (0.5/0.5), so a run of all 1's (explained by staying
+        // in state 1) is more probable than a run of all 0's.
+        let likely = hmm.log_likelihood(&vec![1, 1, 1, 1, 1]);
+        let unlikely = hmm.log_likelihood(&vec![0, 0, 0, 0, 0]);
+        assert!(likely > unlikely);
+    }
+
+    #[test]
+    fn test_log_likelihood_guards_empty_and_invalid_input() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+
+        assert_eq!(hmm.log_likelihood(&vec![]), 0.0);
+        assert_eq!(hmm.log_likelihood(&vec![2]), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn test_with_end_states_validates_size() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+
+        assert!(HiddenMarkov::with_end_states(initials.clone(), st.clone(), obs.clone(), vec![1.]).is_none());
+        assert!(HiddenMarkov::with_end_states(initials, st, obs, vec![1., 1.]).is_some());
+    }
+
+    #[test]
+    fn test_with_end_states_favors_plausible_end_state() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.5, 0.5]];
+        // Only state 0 can plausibly end the sequence
+        let hmm = HiddenMarkov::with_end_states(initials, st, obs, vec![1., 0.0001]).unwrap();
+
+        let estimate = hmm.map_estimate(vec![0, 0, 0]);
+        assert!(estimate.last() == Some(&0));
+    }
+
+    #[test]
+    fn test_expected_length() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        // State 0 has a 0.25 chance of leaving to state 1 on each step
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0., 1.]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+
+        let length = hmm.expected_length(&[0], 0).unwrap();
+        assert!((length - 4.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_kullback_leibler_self_is_zero() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        let obs = vec![ vec![0.5, 0.5],
+                        vec![0.25, 0.75]];
+        let hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+
+        let divergence = hmm.kullback_leibler(&hmm).unwrap();
+        assert!(divergence.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_train_improves_likelihood() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.6, 0.4],
+                       vec![0.4, 0.6]];
+        let obs = vec![ vec![0.6, 0.4],
+                        vec![0.4, 0.6]];
+        let mut hmm = HiddenMarkov::new(initials, st, obs).unwrap();
+        let sequences = vec![vec![0, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 1]];
+
+        let before = hmm.log_likelihood(&sequences[0]);
+        hmm.train(&sequences, 20, 1e-6);
+        let after = hmm.log_likelihood(&sequences[0]);
+
+        // log_likelihood() is log2(P(O|λ)) (never positive), so a better fit means larger
+        assert!(after >= before);
+    }
+
+    #[test]
+    fn test_gaussian_new() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        assert!(GaussianHmm::new(initials, st, vec![0., 10.], vec![1., 1.]).is_some());
+    }
+
+    #[test]
+    fn test_gaussian_new_rejects_nonpositive_variance() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.75, 0.25],
+                       vec![0.25, 0.75]];
+        assert!(GaussianHmm::new(initials, st, vec![0., 10.], vec![1., 0.]).is_none());
+    }
+
+    #[test]
+    fn test_gaussian_map_estimate() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.9, 0.1],
+                       vec![0.1, 0.9]];
+        // State 0 clusters around 0., state 1 clusters around 10.
+        let hmm = GaussianHmm::new(initials, st, vec![0., 10.], vec![1., 1.]).unwrap();
+
+        let estimate = hmm.map_estimate(vec![0.1, -0.2, 9.8, 10.3, 10.1]);
+        assert!(estimate == vec![0, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_gaussian_train_improves_likelihood() {
+        let initials: Vec<f64> = vec![0.5, 0.5];
+        let st = vec![ vec![0.6, 0.4],
+                       vec![0.4, 0.6]];
+        let mut hmm = GaussianHmm::new(initials, st, vec![-1., 1.], vec![2., 2.]).unwrap();
+        let sequences = vec![vec![-1.1, -0.9, -1.2, 1.0, 0.9, 1.1, -1.0, -1.1, 1.2, 1.0]];
+
+        let likelihood = |model: &GaussianHmm| {
+            let alpha = model.forward(&sequences[0]);
+            alpha[sequences[0].len() - 1].log_sum_exp()
+        };
+
+        let before = likelihood(&hmm);
+        hmm.train(&sequences, 20, 1e-6);
+        let after = likelihood(&hmm);
+
+        // forward() returns -log2(P(O|λ)), so a better fit means a smaller value
+        assert!(after <= before);
+    }
+
 }
\ No newline at end of file
@@ -1,5 +1,8 @@
 /// Probabilistic Graphical Models
 
+use std::collections::HashMap;
+use std::f64;
+
 use ndarray::{Array, Ix1, Ix2};
 
 
@@ -8,6 +11,24 @@ type Vector = Array<f64, Ix1>;
 type Matrix = Array<f64, Ix2>;
 
 
+/// log(x), or -∞ if x <= 0. Potentials aren't required to be strictly positive
+/// (see the zero entries in the module tests), so this keeps belief propagation in
+/// the log domain well-defined instead of panicking on `ln()` of zero/negative values.
+fn ln_or_neg_inf(x: f64) -> f64 {
+    if x <= 0. { f64::NEG_INFINITY } else { x.ln() }
+}
+
+/// Combine log-domain values into the log of their summed (linear) values, without
+/// converting back to linear space. Same idea as the -log2 `log_sum_exp` used by
+/// `HiddenMarkov`'s forward pass, just on ordinary (non-negated) natural-log values.
+fn log_sum_exp(values: &[f64]) -> f64 {
+    let max_value = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if max_value == f64::NEG_INFINITY { return f64::NEG_INFINITY }
+    let sum: f64 = values.iter().map(|&x| (x - max_value).exp()).sum();
+    max_value + sum.ln()
+}
+
+
 /// Undirected Graphical Model (also known as Markov Random Field)
 pub struct GraphModel {
     /// List of potential function.
@@ -37,18 +58,112 @@ impl GraphModel {
     /// Can fail if:
     ///  * any potential < 0
     ///  * edges reference nodes without potentials
+    ///  * the edges contain a cycle (sum-product inference is only exact on trees)
     fn new(vertices: Vec<Vector>, edges: Vec<Edge>) -> Option<GraphModel> {
         let n = vertices.len();
-        if edges.iter().any(|ref x| x.u < n && x.v < n){
-            Some(GraphModel { vertices: vertices, edges: edges })
-        } else {
-            None
+        if edges.iter().any(|x| x.u >= n || x.v >= n) { return None }
+
+        let model = GraphModel { vertices: vertices, edges: edges };
+        if model.has_cycle() { return None }
+        Some(model)
+    }
+
+    /// Neighbors of every vertex, as (neighbor, edge index) pairs
+    fn adjacency(&self) -> Vec<Vec<(VertexId, usize)>> {
+        let mut adjacency = vec![Vec::new(); self.vertices.len()];
+        for (e, edge) in self.edges.iter().enumerate() {
+            adjacency[edge.u].push((edge.v, e));
+            adjacency[edge.v].push((edge.u, e));
         }
+        adjacency
     }
 
-    // Marginalization
-    // Compute marginal probability table px for every i ∊ 𝕧.
-    // Using sum - product algorithm as a starting point
+    /// True if the edges contain a cycle, detected with a DFS over each component
+    fn has_cycle(&self) -> bool {
+        let adjacency = self.adjacency();
+        let mut visited = vec![false; self.vertices.len()];
+
+        for start in 0..self.vertices.len() {
+            if visited[start] { continue }
+            visited[start] = true;
+            let mut stack = vec![(start, None)];
+
+            while let Some((v, parent_edge)) = stack.pop() {
+                for &(w, e) in &adjacency[v] {
+                    if Some(e) == parent_edge { continue }
+                    if visited[w] { return true }
+                    visited[w] = true;
+                    stack.push((w, Some(e)));
+                }
+            }
+        }
+        false
+    }
+
+    /// Compute the marginal probability table p(x_i) for every vertex i, using the
+    /// sum-product (belief propagation) algorithm. This is exact because `new` only
+    /// accepts cycle-free edge sets. Works in the log domain with log-sum-exp for
+    /// numerical stability, the same idea `HiddenMarkov`'s forward pass relies on.
+    pub fn marginals(&self) -> Vec<Vector> {
+        let adjacency = self.adjacency();
+        let log_vertices: Vec<Vector> = self.vertices.iter().map(|v| v.mapv(ln_or_neg_inf)).collect();
+        let mut cache: HashMap<(VertexId, VertexId), Vector> = HashMap::new();
+
+        (0..self.vertices.len()).map(|v| {
+            let mut log_belief = log_vertices[v].clone();
+            for &(neighbor, _) in &adjacency[v] {
+                let incoming = self.message(&adjacency, &log_vertices, &mut cache, neighbor, v);
+                log_belief = log_belief + incoming;
+            }
+            normalize(&log_belief)
+        }).collect()
+    }
+
+    /// log m_{from→to}(x_to), i.e. the message vertex `from` sends to its neighbor
+    /// `to`: Σ_{x_from} ψ_from(x_from)·ψ_{from,to}(x_from,x_to)·Π messages into `from`
+    /// from every neighbor except `to`. Memoized in `cache` since a tree's messages
+    /// are shared between the marginal of every vertex that depends on them.
+    fn message(&self, adjacency: &[Vec<(VertexId, usize)>], log_vertices: &[Vector],
+               cache: &mut HashMap<(VertexId, VertexId), Vector>, from: VertexId, to: VertexId) -> Vector
+    {
+        if let Some(m) = cache.get(&(from, to)) { return m.clone() }
+
+        let mut log_belief = log_vertices[from].clone();
+        for &(neighbor, _) in &adjacency[from] {
+            if neighbor == to { continue }
+            let incoming = self.message(adjacency, log_vertices, cache, neighbor, from);
+            log_belief = log_belief + incoming;
+        }
+
+        let edge_index = adjacency[from].iter().find(|&&(w, _)| w == to).unwrap().1;
+        let edge = &self.edges[edge_index];
+        let from_card = self.vertices[from].len();
+        let to_card = self.vertices[to].len();
+
+        let mut out = Vector::zeros(to_card);
+        for x_to in 0..to_card {
+            let terms: Vec<f64> = (0..from_card)
+                .map(|x_from| log_belief[x_from] + ln_or_neg_inf(edge_potential(edge, from, x_from, x_to)))
+                .collect();
+            out[x_to] = log_sum_exp(&terms);
+        }
+
+        cache.insert((from, to), out.clone());
+        out
+    }
+}
+
+/// Potential ψ_{from,to}(x_from, x_to) from an edge, regardless of whether `from` is
+/// the edge's `u` or `v` (the table is always stored as rows=x_u, cols=x_v)
+fn edge_potential(edge: &Edge, from: VertexId, x_from: usize, x_to: usize) -> f64 {
+    if edge.u == from { edge.table[[x_from, x_to]] } else { edge.table[[x_to, x_from]] }
+}
+
+/// Normalize a log-domain belief into a probability distribution summing to 1
+fn normalize(log_belief: &Vector) -> Vector {
+    let values: Vec<f64> = log_belief.iter().cloned().collect();
+    let z = log_sum_exp(&values);
+    log_belief.mapv(|x| (x - z).exp())
 }
 
 impl Edge {
@@ -74,8 +189,9 @@ mod tests {
 
     #[test]
     fn test_new() {
-        let vs = vec![Array::from_vec(vec![1.]),
-                      Array::from_vec(vec![1.])];
+        let vs = vec![Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.])];
         let edges = vec![
             Edge::new(0, 1, Array::from_shape_vec((2, 2), vec![5., 1., 1., 5.]).unwrap()).unwrap(),
             Edge::new(1, 2, Array::from_shape_vec((2, 2), vec![0., 1., 1., 0.]).unwrap()).unwrap(),
@@ -83,4 +199,65 @@ mod tests {
         let m = GraphModel::new(vs, edges);
         assert!(m.is_some());
     }
+
+    #[test]
+    fn test_new_rejects_out_of_bounds_edge() {
+        let vs = vec![Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.])];
+        let edges = vec![
+            Edge::new(0, 1, Array::from_shape_vec((2, 2), vec![5., 1., 1., 5.]).unwrap()).unwrap(),
+            Edge::new(1, 2, Array::from_shape_vec((2, 2), vec![0., 1., 1., 0.]).unwrap()).unwrap(),
+        ];
+        assert!(GraphModel::new(vs, edges).is_none());
+    }
+
+    #[test]
+    fn test_new_rejects_cycle() {
+        let vs = vec![Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.])];
+        let edges = vec![
+            Edge::new(0, 1, Array::from_shape_vec((2, 2), vec![5., 1., 1., 5.]).unwrap()).unwrap(),
+            Edge::new(1, 2, Array::from_shape_vec((2, 2), vec![5., 1., 1., 5.]).unwrap()).unwrap(),
+            Edge::new(2, 0, Array::from_shape_vec((2, 2), vec![5., 1., 1., 5.]).unwrap()).unwrap(),
+        ];
+        assert!(GraphModel::new(vs, edges).is_none());
+    }
+
+    #[test]
+    fn test_marginals_sum_to_one() {
+        let vs = vec![Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.])];
+        let edges = vec![
+            Edge::new(0, 1, Array::from_shape_vec((2, 2), vec![5., 1., 1., 5.]).unwrap()).unwrap(),
+            Edge::new(1, 2, Array::from_shape_vec((2, 2), vec![0., 1., 1., 0.]).unwrap()).unwrap(),
+        ];
+        let m = GraphModel::new(vs, edges).unwrap();
+
+        for p in m.marginals() {
+            let total: f64 = p.iter().sum();
+            assert!((total - 1.).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_marginals_propagate_strong_coupling() {
+        // Vertices 0-1 are strongly coupled to agree, 1-2 are strongly coupled to
+        // disagree, and only vertex 0 has a non-uniform prior (favoring state 0).
+        // That preference should propagate all the way to vertex 2.
+        let vs = vec![Array::from_vec(vec![10., 1.]),
+                      Array::from_vec(vec![1., 1.]),
+                      Array::from_vec(vec![1., 1.])];
+        let edges = vec![
+            Edge::new(0, 1, Array::from_shape_vec((2, 2), vec![10., 1., 1., 10.]).unwrap()).unwrap(),
+            Edge::new(1, 2, Array::from_shape_vec((2, 2), vec![1., 10., 10., 1.]).unwrap()).unwrap(),
+        ];
+        let m = GraphModel::new(vs, edges).unwrap();
+        let marginals = m.marginals();
+
+        assert!(marginals[0][0] > marginals[0][1]);
+        assert!(marginals[1][0] > marginals[1][1]);
+        assert!(marginals[2][1] > marginals[2][0]);
+    }
 }
\ No newline at end of file